@@ -6,10 +6,12 @@
 */
 
 //----- crates
+use std::io;
 use std::io::prelude::*;
+use std::fmt::Write as _;
 use std::fs::File;
 use std::path;
-use std::collections::HashMap;
+use std::collections::HashSet;
 use console::Term;
 
 
@@ -34,175 +36,518 @@ impl Opcodes {
     pub const JUMP_BCK: u8       = 93;       // ']' jump backward if data value is not 0
 }
 
+// Compiled intermediate representation
+//
+// `VMCore::compute_jumps` lowers the raw byte stream into a vector of these
+// before `execute` ever runs, so the interpreter never has to re-dispatch on
+// individual '+'/'-'/'<'/'>' bytes or look jump targets up in a map.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Add(i32),               // add n to the value at the data pointer (wrapping)
+    Move(isize),            // move the data pointer by n cells (wrapping or growing, per `TapeConfig`)
+    Out,                    // write the current cell to the screen
+    In,                     // read a char from the user into the current cell
+    SetZero,                // set the current cell to 0 directly
+    JumpIfZero(usize),      // '[' : jump to the op index if the current cell is 0
+    JumpIfNotZero(usize),   // ']' : jump to the op index if the current cell is not 0
+}
+
+// the two ways a bracket can fail to match up with its counterpart
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BracketError {
+    UnmatchedOpen,    // a '[' that is never closed
+    UnmatchedClose,   // a ']' with no matching '['
+}
+
+// Runtime traps surfaced by the VM when execution cannot continue normally
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Trap {
+    UnbalancedBrackets { pos: usize, kind: BracketError },  // byte offset of the offending bracket
+    InputError { pc: usize },           // READ_CHAR could not obtain a valid value
+    IoError { pc: usize },              // a file or stdout operation failed
+    CycleLimitExceeded { pc: usize },   // the configured instruction budget ran out
+    TapeUnderflow { pc: usize },        // data pointer moved left of cell 0 with wrapping disabled
+}
+
+// how wide a data cell is, and therefore what `data_value_inc`/`data_value_dec`
+// wrap around on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CellWidth {
+    Bits8,
+    Bits16,
+    Bits32,
+}
+
+impl CellWidth {
+    // the value one past the highest value a cell of this width can hold
+    fn modulus(self) -> i64 {
+        match self {
+            CellWidth::Bits8 => 1 << 8,
+            CellWidth::Bits16 => 1 << 16,
+            CellWidth::Bits32 => 1 << 32,
+        }
+    }
+}
+
+// what `READ_CHAR` writes to the current cell when no input is available
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EofBehavior {
+    SetZero,       // write 0
+    SetMinusOne,   // write the cell width's highest value (all bits set)
+    Unchanged,     // leave the cell as it was
+}
+
+// knobs controlling the shape and edge behaviour of the tape
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TapeConfig {
+    pub initial_size: usize,      // number of cells allocated up front
+    pub auto_grow: bool,          // push a new zero cell instead of wrapping at the right edge
+    pub wrap_left: bool,          // wrap instead of trapping when moving left of cell 0
+    pub cell_width: CellWidth,    // wrapping width used by data_value_inc/data_value_dec
+    pub eof_behavior: EofBehavior, // what READ_CHAR writes on end-of-input
+}
+
+impl Default for TapeConfig {
+    // matches the VM's original hard-wired behaviour: a fixed 32,768 cell
+    // tape of wrapping 8-bit cells, wrapping at both edges
+    fn default() -> Self {
+        TapeConfig {
+            initial_size: DATA_SIZE,
+            auto_grow: false,
+            wrap_left: true,
+            cell_width: CellWidth::Bits8,
+            eof_behavior: EofBehavior::Unchanged,
+        }
+    }
+}
+
 // Brainfuck Virtual Machine Core
 pub struct VMCore {
     pc: usize,                          // program counter
     dp: usize,                          // data pointer
-    code: Vec<u8>,                      // array holding the code
-    data: Vec<u8>,                      // array holding the data
+    code: Vec<u8>,                      // array holding the raw source code
+    data: Vec<u32>,                     // array holding the data
+    ops: Vec<Op>,                       // compiled instructions executed by the VM
+
+    cycles: u64,                        // number of instructions dispatched so far
+    max_cycles: Option<u64>,            // optional instruction budget
+
+    breakpoints: HashSet<usize>,        // op indices at which `cont` should pause
 
-    jumps: HashMap<usize, usize>,       // jumps hashmap
+    config: TapeConfig,                 // tape size/wrapping/cell-width/EOF behaviour
 }
 
 // implementation
 impl VMCore {
     //----- private functions
 
-    // compute the jumps hashmap
-    fn compute_jumps(&mut self, nbytes: usize) -> Result<(), String> {
-        // temporary stack to hold jumps location
-        let mut stack: Vec<usize> = Vec::new();
-        let mut counter: usize = 0;
+    // lower the raw byte stream into the compiled `ops` vector
+    //
+    // Runs of '+'/'-' and '<'/'>' are coalesced into single `Add`/`Move`
+    // instructions (cancelling pairs collapse away entirely), the `[-]`/`[+]`
+    // clear-loop idiom is recognized and replaced by a single `SetZero`, and
+    // every other '['/']' pair is emitted as a `JumpIfZero`/`JumpIfNotZero`
+    // carrying the resolved index just past its matching instruction.
+    fn compute_jumps(&mut self, nbytes: usize) -> Result<(), Trap> {
+        self.ops.clear();
 
-        // parse the code for '[' and ']'
-        while counter < nbytes {
-            let opcode = self.code[counter];
+        // stack of (byte position, op index) for the still-open 'JumpIfZero' instructions
+        let mut stack: Vec<(usize, usize)> = Vec::new();
 
-            // jump forward
-            if opcode == Opcodes::JUMP_FWD {
-                stack.push(counter);
-            }
+        let mut i = 0;
+        while i < nbytes {
+            let opcode = self.code[i];
 
-            // jump backward
-            if opcode == Opcodes::JUMP_BCK {
-                // empty stack => Error
-                if stack.len() == 0 {
-                    return Err("Error: unbalanced number of '[' and ']' in the source code!".to_string());
+            match opcode {
+                Opcodes::DATA_VALUE_INC | Opcodes::DATA_VALUE_DEC => {
+                    // widened past i16 so a maximal-length run (up to `CODE_SIZE`
+                    // consecutive '+'/'-') cannot overflow the counter
+                    let mut count: i32 = 0;
+                    while i < nbytes && matches!(self.code[i], Opcodes::DATA_VALUE_INC | Opcodes::DATA_VALUE_DEC) {
+                        count += if self.code[i] == Opcodes::DATA_VALUE_INC { 1 } else { -1 };
+                        i += 1;
+                    }
+                    if count != 0 {
+                        self.ops.push(Op::Add(count));
+                    }
                 }
 
-                // retrieve the last value found for JumpFwd
-                let value = stack.pop().unwrap();
+                Opcodes::DATA_PTR_INC | Opcodes::DATA_PTR_DEC => {
+                    let mut offset: isize = 0;
+                    while i < nbytes && matches!(self.code[i], Opcodes::DATA_PTR_INC | Opcodes::DATA_PTR_DEC) {
+                        offset += if self.code[i] == Opcodes::DATA_PTR_INC { 1 } else { -1 };
+                        i += 1;
+                    }
+                    if offset != 0 {
+                        self.ops.push(Op::Move(offset));
+                    }
+                }
 
-                // insert the two values in the HashMap
-                self.jumps.insert(value, counter + 1);      // '[' is map to the position after the matching ']'
-                self.jumps.insert(counter, value + 1);      // ']' is map to the position after the matching '['
-            }
+                Opcodes::WRITE_CHAR => {
+                    self.ops.push(Op::Out);
+                    i += 1;
+                }
 
-            // next opcode
-            counter += 1;
-        }
+                Opcodes::READ_CHAR => {
+                    self.ops.push(Op::In);
+                    i += 1;
+                }
 
-        // last check for missing closing jump
-        if stack.len() > 0 {
-            return Err("Error: unbalanced number of '[' and ']' in the source code!".to_string());
-        }
+                Opcodes::JUMP_FWD => {
+                    // recognize the "[-]" / "[+]" idiom: a loop body that only
+                    // nets a single decrement/increment of the current cell
+                    // always clears it, so emit `SetZero` instead of a loop
+                    if i + 2 < nbytes
+                        && matches!(self.code[i + 1], Opcodes::DATA_VALUE_INC | Opcodes::DATA_VALUE_DEC)
+                        && self.code[i + 2] == Opcodes::JUMP_BCK
+                    {
+                        self.ops.push(Op::SetZero);
+                        i += 3;
+                        continue;
+                    }
 
-        Ok(())
-    }
+                    // placeholder target, patched once the matching ']' is found
+                    stack.push((i, self.ops.len()));
+                    self.ops.push(Op::JumpIfZero(0));
+                    i += 1;
+                }
 
-    fn data_value_inc(&mut self) {
-        let mut value: i16 = self.data[self.dp] as i16;
-        value = (value + 1) & 255;
-        self.data[self.dp] = value as u8;
-    }
+                Opcodes::JUMP_BCK => {
+                    // empty stack => a stray ']' with no matching '['
+                    let (_, open_index) = match stack.pop() {
+                        Some(entry) => entry,
+                        None => return Err(Trap::UnbalancedBrackets { pos: i, kind: BracketError::UnmatchedClose }),
+                    };
 
-    fn data_value_dec(&mut self) {
-        let mut value: i16 = self.data[self.dp] as i16;
-        value = (value - 1) & 255;
-        self.data[self.dp] = value as u8;
-    }
+                    let close_index = self.ops.len();
+                    self.ops.push(Op::JumpIfNotZero(open_index + 1));
 
-    fn data_ptr_inc(&mut self) {
-        self.dp = (self.dp + 1) & (DATA_SIZE - 1);
-    }
+                    // patch the opening jump to land right after this closing jump
+                    self.ops[open_index] = Op::JumpIfZero(close_index + 1);
+                    i += 1;
+                }
 
-    fn data_ptr_dec(&mut self) {
-        let mut value: i32 = self.dp as i32;
-        let max = DATA_SIZE as i32;
-        value = (value - 1) & (max - 1) ;
-        self.dp = value as usize;
-    }
+                // unknown byte, skip it
+                _ => i += 1,
+            }
+        }
 
-    fn jump_fwd(&mut self) {
-        if self.data[self.dp] == 0 {
-            let value = self.pc - 1;
-            self.pc = self.jumps[&value];
+        // last check for one or more '[' that were never closed
+        if let Some(&(pos, _)) = stack.last() {
+            return Err(Trap::UnbalancedBrackets { pos, kind: BracketError::UnmatchedOpen });
         }
+
+        Ok(())
     }
 
-    fn jump_bck(&mut self) {
-        if self.data[self.dp] != 0 {
-            let value = self.pc - 1;
-            self.pc = self.jumps[&value];
-        }
+    fn op_add(&mut self, count: i32) {
+        let modulus = self.config.cell_width.modulus();
+        let value = (self.data[self.dp] as i64 + count as i64).rem_euclid(modulus);
+        self.data[self.dp] = value as u32;
+    }
+
+    // move the data pointer by `offset` cells, honouring the configured
+    // edge behaviour: auto-grow (push new zero cells) or wrap at the right
+    // edge, and wrap or trap at the left edge
+    fn op_move(&mut self, offset: isize) -> Result<(), Trap> {
+        let target = self.dp as isize + offset;
+
+        let new_dp = if target < 0 {
+            if self.config.wrap_left {
+                target.rem_euclid(self.data.len() as isize) as usize
+            } else {
+                return Err(Trap::TapeUnderflow { pc: self.pc - 1 });
+            }
+        } else if (target as usize) >= self.data.len() {
+            if self.config.auto_grow {
+                self.data.resize(target as usize + 1, 0);
+                target as usize
+            } else {
+                target.rem_euclid(self.data.len() as isize) as usize
+            }
+        } else {
+            target as usize
+        };
+
+        self.dp = new_dp;
+        Ok(())
     }
 
     //----- public functions
 
-    // constructor
+    // constructor, using the default tape configuration (a fixed 32,768
+    // cell tape of wrapping 8-bit cells)
     pub fn new() -> Self {
+        Self::with_config(TapeConfig::default())
+    }
+
+    // constructor allowing the tape's size, growth, wrapping and cell-width
+    // behaviour to be customized
+    pub fn with_config(config: TapeConfig) -> Self {
         VMCore {
             pc: 0,
             dp: 0,
             code: Vec::with_capacity(CODE_SIZE),
-            data: vec![0; DATA_SIZE],
-            jumps: HashMap::new()
+            data: vec![0; config.initial_size],
+            ops: Vec::new(),
+
+            cycles: 0,
+            max_cycles: None,
+
+            breakpoints: HashSet::new(),
+
+            config,
         }
     }
 
+    // set the instruction budget; `None` means unbounded (the default)
+    pub fn set_max_cycles(&mut self, max_cycles: Option<u64>) {
+        self.max_cycles = max_cycles;
+    }
+
+    // number of instructions dispatched so far, summed over every
+    // `execute`, `step` and `cont` call; the count is never reset
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    // current program counter, as an index into the compiled instructions
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    // current data pointer
+    pub fn dp(&self) -> usize {
+        self.dp
+    }
+
+    // stop `cont` right before dispatching the instruction at this index
+    pub fn set_breakpoint(&mut self, pc: usize) {
+        self.breakpoints.insert(pc);
+    }
+
+    // remove a previously set breakpoint, if any
+    pub fn clear_breakpoint(&mut self, pc: usize) {
+        self.breakpoints.remove(&pc);
+    }
+
     // read the code from a file
-    pub fn load(&mut self, filename: &str) -> Result<usize, String> {
+    pub fn load(&mut self, filename: &str) -> Result<usize, Trap> {
         // check for the file
         if !path::Path::new(&filename).exists() {
-            return Err("Unable to find the file!".to_string());
+            return Err(Trap::IoError { pc: 0 });
         }
 
         // read the code
         let mut program: [u8; CODE_SIZE] = [0; CODE_SIZE];
-        let mut file = File::open(filename).unwrap();
-        let n = file.read(&mut program[..]).unwrap();
+        let mut file = File::open(filename).map_err(|_| Trap::IoError { pc: 0 })?;
+        let n = file.read(&mut program[..]).map_err(|_| Trap::IoError { pc: 0 })?;
+
+        // insert the code into the structure, keeping only the bytes actually
+        // read so source-span diagnostics don't run off into the zeroed tail
+        // of the read buffer
+        self.code.extend_from_slice(&program[..n]);
 
-        // insert the code into the structure
-        for i in program {
-            self.code.push(i);
+        // compile the code into the internal instruction vector
+        self.compute_jumps(n)?;
+
+        // return the number of bytes read
+        Ok(n)
+    }
+
+    // execute the code from the current position to completion
+    pub fn execute(&mut self) -> Result<(), Trap> {
+        while self.pc < self.ops.len() {
+            // stop rather than spin forever once the instruction budget runs out
+            if let Some(max_cycles) = self.max_cycles {
+                if self.cycles >= max_cycles {
+                    return Err(Trap::CycleLimitExceeded { pc: self.pc });
+                }
+            }
+
+            self.dispatch_one()?;
         }
 
-        // compute the jumps
-        self.compute_jumps(n).unwrap();
+        Ok(())
+    }
 
-        for (k, v) in &self.jumps {
-            println!("[{}] = {}", k, v);
+    // execute exactly one instruction and return the resulting (pc, dp); a
+    // no-op once the program has run off the end of the instruction vector
+    pub fn step(&mut self) -> Result<(usize, usize), Trap> {
+        if self.pc < self.ops.len() {
+            self.dispatch_one()?;
         }
 
-        // return the number of bytes read
-        Ok(n)
+        Ok((self.pc, self.dp))
     }
 
-    // execute the code
-    pub fn execute(&mut self) {
-        while self.pc < self.code.len() {
-            // read the next opcode and increment the program counter
-            let opcode = self.code[self.pc];
-            self.pc = self.pc + 1;
+    // run until a breakpoint is reached, the program halts, or a trap fires
+    pub fn cont(&mut self) -> Result<(), Trap> {
+        while self.pc < self.ops.len() && !self.breakpoints.contains(&self.pc) {
+            if let Some(max_cycles) = self.max_cycles {
+                if self.cycles >= max_cycles {
+                    return Err(Trap::CycleLimitExceeded { pc: self.pc });
+                }
+            }
 
-            // opcode lookup
-            match opcode {
+            self.dispatch_one()?;
+        }
 
-                Opcodes::DATA_VALUE_INC => self.data_value_inc(),
-                Opcodes::DATA_VALUE_DEC => self.data_value_dec(),
-                Opcodes::DATA_PTR_INC => self.data_ptr_inc(),
-                Opcodes::DATA_PTR_DEC => self.data_ptr_dec(),
-                Opcodes::JUMP_FWD => self.jump_fwd(),
-                Opcodes::JUMP_BCK => self.jump_bck(),
+        Ok(())
+    }
 
-                Opcodes::WRITE_CHAR => {
-                    print!("{}", self.data[self.dp] as char);
-                },
+    // print a window of the tape, `len` cells starting at `start`, with the
+    // cell under the data pointer highlighted
+    pub fn dump_tape(&self, start: usize, len: usize) {
+        println!("{}", self.render_tape(start, len));
+    }
 
-                Opcodes::READ_CHAR => {
-                    let t = Term::stdout();
-                    match t.read_char() {
-                        Ok(value) => {
-                            self.data[self.dp] = (value.to_digit(10).unwrap() & 255) as u8;
-                        },
-                        _ => continue
+    //----- private functions (continued)
+
+    // dispatch and execute the single instruction at `pc`, advancing `pc`
+    // (and any jump targets) and `cycles` as it goes; shared by `execute`,
+    // `step` and `cont` so the per-opcode logic only lives in one place
+    fn dispatch_one(&mut self) -> Result<(), Trap> {
+        // read the next instruction and increment the program counter
+        let op = self.ops[self.pc];
+        self.pc += 1;
+        self.cycles += 1;
+
+        // instruction lookup
+        match op {
+            Op::Add(count) => self.op_add(count),
+            Op::Move(offset) => self.op_move(offset)?,
+            Op::SetZero => self.data[self.dp] = 0,
+
+            Op::Out => {
+                let c = char::from_u32(self.data[self.dp]).unwrap_or('\u{fffd}');
+                write!(io::stdout(), "{}", c)
+                    .map_err(|_| Trap::IoError { pc: self.pc - 1 })?;
+            }
+
+            Op::In => {
+                let t = Term::stdout();
+                match t.read_char() {
+                    Ok(value) => {
+                        let digit = value.to_digit(10).ok_or(Trap::InputError { pc: self.pc - 1 })?;
+                        self.data[self.dp] = digit;
                     }
+
+                    // end-of-input: apply the configured behaviour instead of
+                    // trapping. `console` reports this as `UnexpectedEof` on a
+                    // real tty and as `NotConnected` when stdin isn't attached
+                    // to one (e.g. piped/redirected input). Any other I/O
+                    // failure is a genuine error and still propagates as a
+                    // trap (see chunk0-3).
+                    Err(err)
+                        if matches!(err.kind(), io::ErrorKind::UnexpectedEof | io::ErrorKind::NotConnected) =>
+                    {
+                        match self.config.eof_behavior {
+                            EofBehavior::SetZero => self.data[self.dp] = 0,
+                            EofBehavior::SetMinusOne => {
+                                self.data[self.dp] = (self.config.cell_width.modulus() - 1) as u32;
+                            }
+                            EofBehavior::Unchanged => {}
+                        }
+                    }
+
+                    Err(_) => return Err(Trap::InputError { pc: self.pc - 1 }),
                 }
+            }
 
-                // unknown opcode
-                _ => continue
+            Op::JumpIfZero(target) => {
+                if self.data[self.dp] == 0 {
+                    self.pc = target;
+                }
+            }
+
+            Op::JumpIfNotZero(target) => {
+                if self.data[self.dp] != 0 {
+                    self.pc = target;
+                }
             }
         }
+
+        Ok(())
+    }
+
+    // render the tape window as a string; split out from `dump_tape` so it
+    // can be tested without capturing stdout
+    fn render_tape(&self, start: usize, len: usize) -> String {
+        let end = (start + len).min(self.data.len());
+        let mut out = String::new();
+
+        for i in start..end {
+            if i == self.dp {
+                let _ = write!(out, "[{}]", self.data[i]);
+            } else {
+                let _ = write!(out, " {} ", self.data[i]);
+            }
+        }
+
+        out
+    }
+
+    // render a trap as a source-spanned diagnostic, with a caret pointing at
+    // the offending byte; traps with no useful source position fall back to
+    // their plain debug representation
+    pub fn diagnostic(&self, trap: &Trap) -> String {
+        match *trap {
+            Trap::UnbalancedBrackets { pos, kind } => {
+                let message = match kind {
+                    BracketError::UnmatchedOpen => "this '[' is never closed",
+                    BracketError::UnmatchedClose => "stray ']' has no matching '['",
+                };
+                self.render_span(pos, message)
+            }
+            _ => format!("{:?}", trap),
+        }
+    }
+
+    // format a one-line/one-caret diagnostic pointing at byte offset `pos`
+    fn render_span(&self, pos: usize, message: &str) -> String {
+        let (line, column) = self.line_col(pos);
+        let source_line = self.source_line(line);
+
+        format!(
+            "error: {}\n  --> line {}, column {}\n{}\n{:>width$}",
+            message, line, column, source_line, "^", width = column
+        )
+    }
+
+    // 1-indexed (line, column) of byte offset `pos`, found by scanning for newlines
+    fn line_col(&self, pos: usize) -> (usize, usize) {
+        let mut line = 1;
+        let mut column = 1;
+
+        for &b in &self.code[..pos.min(self.code.len())] {
+            if b == b'\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+
+        (line, column)
+    }
+
+    // text of the given 1-indexed source line
+    fn source_line(&self, line: usize) -> String {
+        let mut current = 1;
+        let mut start = 0;
+
+        for (i, &b) in self.code.iter().enumerate() {
+            if b == b'\n' {
+                if current == line {
+                    return String::from_utf8_lossy(&self.code[start..i]).into_owned();
+                }
+                current += 1;
+                start = i + 1;
+            }
+        }
+
+        String::from_utf8_lossy(&self.code[start..]).into_owned()
     }
 }
 
@@ -231,7 +576,7 @@ mod tests {
         assert_eq!(a.dp, 0);
         assert_eq!(a.code.capacity(), CODE_SIZE);
         assert_eq!(a.data.len(), DATA_SIZE);
-        assert_eq!(a.jumps.len(), 0);
+        assert_eq!(a.ops.len(), 0);
     }
 
     #[test]
@@ -242,7 +587,7 @@ mod tests {
         a.code.push(Opcodes::JUMP_BCK);
         a.code.push(Opcodes::JUMP_BCK);
 
-        assert_eq!(a.compute_jumps(4), Err("Error: unbalanced number of '[' and ']' in the source code!".to_string()))
+        assert_eq!(a.compute_jumps(4), Err(Trap::UnbalancedBrackets { pos: 3, kind: BracketError::UnmatchedClose }))
     }
 
     #[test]
@@ -253,20 +598,73 @@ mod tests {
         a.code.push(Opcodes::DATA_VALUE_INC);
         a.code.push(Opcodes::JUMP_BCK);
 
-        assert_eq!(a.compute_jumps(4), Err("Error: unbalanced number of '[' and ']' in the source code!".to_string()))
+        assert_eq!(a.compute_jumps(4), Err(Trap::UnbalancedBrackets { pos: 0, kind: BracketError::UnmatchedOpen }))
     }
 
     #[test]
     fn compute_jumps_one_loop_correct() {
         let mut a = VMCore::new();
 
-        // insert the code and compute jumps
+        // insert the code and compile it
         insert_code(&mut a);
         a.compute_jumps(11).unwrap();
 
-        // assess if the jumps are correctly computed
-        assert_eq!(a.jumps[&5], 11);
-        assert_eq!(a.jumps[&10], 6);
+        // assess if the jump targets are correctly resolved
+        assert_eq!(a.ops[1], Op::JumpIfZero(7));
+        assert_eq!(a.ops[6], Op::JumpIfNotZero(2));
+    }
+
+    #[test]
+    fn compile_fuses_consecutive_add_and_move() {
+        let mut a = VMCore::new();
+        a.code.push(Opcodes::DATA_VALUE_INC);
+        a.code.push(Opcodes::DATA_VALUE_INC);
+        a.code.push(Opcodes::DATA_VALUE_DEC);
+        a.code.push(Opcodes::DATA_PTR_INC);
+        a.code.push(Opcodes::DATA_PTR_INC);
+        a.code.push(Opcodes::DATA_PTR_DEC);
+
+        a.compute_jumps(6).unwrap();
+
+        assert_eq!(a.ops, vec![Op::Add(1), Op::Move(1)]);
+    }
+
+    #[test]
+    fn compile_collapses_cancelling_runs_to_nothing() {
+        let mut a = VMCore::new();
+        a.code.push(Opcodes::DATA_PTR_INC);
+        a.code.push(Opcodes::DATA_PTR_DEC);
+        a.code.push(Opcodes::WRITE_CHAR);
+
+        a.compute_jumps(3).unwrap();
+
+        assert_eq!(a.ops, vec![Op::Out]);
+    }
+
+    #[test]
+    fn compile_detects_clear_loop_idiom() {
+        let mut a = VMCore::new();
+        a.code.push(Opcodes::JUMP_FWD);
+        a.code.push(Opcodes::DATA_VALUE_DEC);
+        a.code.push(Opcodes::JUMP_BCK);
+
+        a.compute_jumps(3).unwrap();
+
+        assert_eq!(a.ops, vec![Op::SetZero]);
+    }
+
+    #[test]
+    fn execute_clear_loop_zeroes_cell() {
+        let mut a = VMCore::new();
+        a.data[0] = 42;
+        a.code.push(Opcodes::JUMP_FWD);
+        a.code.push(Opcodes::DATA_VALUE_INC);
+        a.code.push(Opcodes::JUMP_BCK);
+
+        a.compute_jumps(3).unwrap();
+        a.execute().unwrap();
+
+        assert_eq!(a.data[0], 0);
     }
 
     #[test]
@@ -279,7 +677,8 @@ mod tests {
         a.code.push(Opcodes::DATA_VALUE_INC);
         a.code.push(Opcodes::DATA_VALUE_INC);
 
-        a.execute();
+        a.compute_jumps(5).unwrap();
+        a.execute().unwrap();
 
         assert_eq!(a.data[0], 5);
     }
@@ -295,7 +694,8 @@ mod tests {
         a.code.push(Opcodes::DATA_VALUE_DEC);
 
         a.data[0] = 7;
-        a.execute();
+        a.compute_jumps(5).unwrap();
+        a.execute().unwrap();
 
         assert_eq!(a.data[0], 2);
     }
@@ -310,7 +710,8 @@ mod tests {
         a.code.push(Opcodes::DATA_VALUE_INC);
         a.code.push(Opcodes::DATA_VALUE_INC);
         a.code.push(Opcodes::DATA_VALUE_INC);
-        a.execute();
+        a.compute_jumps(5).unwrap();
+        a.execute().unwrap();
 
         assert_eq!(a.data[0], 2);
     }
@@ -325,7 +726,8 @@ mod tests {
         a.code.push(Opcodes::DATA_VALUE_DEC);
         a.code.push(Opcodes::DATA_VALUE_DEC);
         a.code.push(Opcodes::DATA_VALUE_DEC);
-        a.execute();
+        a.compute_jumps(5).unwrap();
+        a.execute().unwrap();
 
         assert_eq!(a.data[0], 253);
     }
@@ -335,7 +737,8 @@ mod tests {
         let mut a = VMCore::new();
 
         a.code.push(Opcodes::DATA_PTR_INC);
-        a.execute();
+        a.compute_jumps(1).unwrap();
+        a.execute().unwrap();
 
         assert_eq!(a.dp, 1);
     }
@@ -349,7 +752,8 @@ mod tests {
         a.code.push(Opcodes::DATA_PTR_INC);
         a.code.push(Opcodes::DATA_PTR_INC);
         a.code.push(Opcodes::DATA_PTR_INC);
-        a.execute();
+        a.compute_jumps(4).unwrap();
+        a.execute().unwrap();
 
         assert_eq!(a.dp, 2);
     }
@@ -360,7 +764,8 @@ mod tests {
 
         a.dp = 10;
         a.code.push(Opcodes::DATA_PTR_DEC);
-        a.execute();
+        a.compute_jumps(1).unwrap();
+        a.execute().unwrap();
 
         assert_eq!(a.dp, 9);
     }
@@ -374,74 +779,243 @@ mod tests {
         a.code.push(Opcodes::DATA_PTR_DEC);
         a.code.push(Opcodes::DATA_PTR_DEC);
         a.code.push(Opcodes::DATA_PTR_DEC);
-        a.execute();
+        a.compute_jumps(4).unwrap();
+        a.execute().unwrap();
 
         assert_eq!(a.dp, DATA_SIZE - 2);
     }
 
     #[test]
-    fn jump_fwd_not_zero() {
+    fn op_move_auto_grow_pushes_new_cells_past_the_end() {
+        let mut a = VMCore::with_config(TapeConfig { initial_size: 2, auto_grow: true, ..TapeConfig::default() });
 
+        a.dp = 1;
+        a.code.push(Opcodes::DATA_PTR_INC);
+        a.code.push(Opcodes::DATA_PTR_INC);
+        a.compute_jumps(2).unwrap();
+        a.execute().unwrap();
+
+        assert_eq!(a.dp, 3);
+        assert_eq!(a.data.len(), 4);
+    }
+
+    #[test]
+    fn op_move_traps_on_underflow_when_wrap_left_is_disabled() {
+        let mut a = VMCore::with_config(TapeConfig { wrap_left: false, ..TapeConfig::default() });
+
+        a.code.push(Opcodes::DATA_PTR_DEC);
+        a.compute_jumps(1).unwrap();
+
+        assert_eq!(a.execute(), Err(Trap::TapeUnderflow { pc: 0 }));
+    }
+
+    #[test]
+    fn op_add_wraps_according_to_configured_cell_width() {
+        let mut a = VMCore::with_config(TapeConfig { cell_width: CellWidth::Bits16, ..TapeConfig::default() });
+
+        a.data[0] = 65535;
+        a.code.push(Opcodes::DATA_VALUE_INC);
+        a.compute_jumps(1).unwrap();
+        a.execute().unwrap();
+
+        assert_eq!(a.data[0], 0);
+    }
+
+    #[test]
+    fn execute_runs_loop_to_completion() {
         let mut a = VMCore::new();
 
-        // insert the code and compute the jumps
+        // insert the code and compile it
         insert_code(&mut a);
         a.compute_jumps(11).unwrap();
+        a.execute().unwrap();
+
+        // the loop moves all 5 units from cell 0 to cell 1
+        assert_eq!(a.data[0], 0);
+        assert_eq!(a.data[1], 5);
+        assert_eq!(a.dp, 0);
+    }
+
+    #[test]
+    fn execute_skips_loop_body_when_already_zero() {
+        let mut a = VMCore::new();
 
-        // change registers
-        a.data[0] = 5;
-        a.pc = 6;
+        // a bare loop with no preceding increments: cell 0 starts at 0
+        a.code.push(Opcodes::JUMP_FWD);
+        a.code.push(Opcodes::DATA_PTR_INC);
+        a.code.push(Opcodes::DATA_VALUE_INC);
+        a.code.push(Opcodes::DATA_PTR_DEC);
+        a.code.push(Opcodes::DATA_VALUE_DEC);
+        a.code.push(Opcodes::JUMP_BCK);
 
-        // compute forward jump
-        a.jump_fwd();
+        a.compute_jumps(6).unwrap();
+        a.execute().unwrap();
 
-        assert_eq!(a.pc, 6);
+        assert_eq!(a.data[0], 0);
+        assert_eq!(a.data[1], 0);
     }
 
     #[test]
-    fn jmp_fwd_zero() {
+    fn execute_stops_at_cycle_limit() {
         let mut a = VMCore::new();
 
-        // insert the code and compute the jumps
-        insert_code(&mut a);
-        a.compute_jumps(11).unwrap();
+        // an infinite loop: '+' followed by '[]' never exits since the cell is never zero
+        a.code.push(Opcodes::DATA_VALUE_INC);
+        a.code.push(Opcodes::JUMP_FWD);
+        a.code.push(Opcodes::JUMP_BCK);
 
-        // jump
-        a.pc = 6;
-        a.jump_fwd();
+        a.compute_jumps(3).unwrap();
+        a.set_max_cycles(Some(3));
 
-        assert_eq!(a.pc, 11);
+        assert_eq!(a.execute(), Err(Trap::CycleLimitExceeded { pc: 2 }));
+        assert_eq!(a.cycles(), 3);
     }
 
     #[test]
-    fn jump_bck_not_zero() {
+    fn execute_reports_cycles_spent() {
         let mut a = VMCore::new();
 
-        // insert the code and compute the jumps
         insert_code(&mut a);
         a.compute_jumps(11).unwrap();
+        a.execute().unwrap();
 
-        // jump
-        a.data[0] = 5;
-        a.pc = 11;
-        a.jump_bck();
+        // Add(5), JumpIfZero, then 5 iterations of (Move, Add, Move, Add, JumpIfNotZero)
+        assert_eq!(a.cycles(), 27);
+    }
 
-        assert_eq!(a.pc, 6);
+    #[test]
+    fn load_missing_file_returns_io_error() {
+        let mut a = VMCore::new();
+
+        assert_eq!(a.load("/no/such/file.bf"), Err(Trap::IoError { pc: 0 }));
     }
 
     #[test]
-    fn jump_bck_zero() {
+    fn diagnostic_points_at_unclosed_bracket_on_its_own_line() {
         let mut a = VMCore::new();
 
-        // insert the code and compute the jumps
-        insert_code(&mut a);
-        a.compute_jumps(11).unwrap();
+        // line 1: "++", line 2: "[+" (the '[' on line 2 is never closed)
+        for b in b"++\n[+" {
+            a.code.push(*b);
+        }
+
+        let err = a.compute_jumps(5).unwrap_err();
+
+        assert_eq!(err, Trap::UnbalancedBrackets { pos: 3, kind: BracketError::UnmatchedOpen });
+        assert_eq!(
+            a.diagnostic(&err),
+            "error: this '[' is never closed\n  --> line 2, column 1\n[+\n^"
+        );
+    }
+
+    #[test]
+    fn diagnostic_points_at_stray_closing_bracket() {
+        let mut a = VMCore::new();
+
+        for b in b"+]" {
+            a.code.push(*b);
+        }
+
+        let err = a.compute_jumps(2).unwrap_err();
+
+        assert_eq!(err, Trap::UnbalancedBrackets { pos: 1, kind: BracketError::UnmatchedClose });
+        assert_eq!(
+            a.diagnostic(&err),
+            "error: stray ']' has no matching '['\n  --> line 1, column 2\n+]\n ^"
+        );
+    }
+
+    #[test]
+    fn step_executes_one_instruction_at_a_time() {
+        let mut a = VMCore::new();
+        a.code.push(Opcodes::DATA_VALUE_INC);
+        a.code.push(Opcodes::DATA_PTR_INC);
+        a.compute_jumps(2).unwrap();
 
-        // jump
-        a.data[0] = 0;
-        a.pc = 11;
-        a.jump_bck();
+        assert_eq!(a.step().unwrap(), (1, 0));
+        assert_eq!(a.data[0], 1);
+
+        assert_eq!(a.step().unwrap(), (2, 1));
+    }
+
+    #[test]
+    fn step_is_a_no_op_past_the_end_of_the_program() {
+        let mut a = VMCore::new();
+        a.code.push(Opcodes::DATA_VALUE_INC);
+        a.compute_jumps(1).unwrap();
+
+        a.step().unwrap();
+        assert_eq!(a.step().unwrap(), (1, 0));
+    }
+
+    #[test]
+    fn cont_stops_right_before_a_breakpoint() {
+        let mut a = VMCore::new();
+        a.code.push(Opcodes::DATA_VALUE_INC);
+        a.code.push(Opcodes::DATA_PTR_INC);
+        a.code.push(Opcodes::DATA_VALUE_INC);
+        a.compute_jumps(3).unwrap();
+
+        a.set_breakpoint(2);
+        a.cont().unwrap();
+
+        assert_eq!(a.pc(), 2);
+        assert_eq!(a.data[0], 1);
+        assert_eq!(a.dp(), 1);
+    }
+
+    #[test]
+    fn cont_runs_to_completion_without_a_breakpoint() {
+        let mut a = VMCore::new();
+        a.code.push(Opcodes::DATA_VALUE_INC);
+        a.code.push(Opcodes::DATA_PTR_INC);
+        a.compute_jumps(2).unwrap();
+
+        a.cont().unwrap();
+
+        assert_eq!(a.pc(), 2);
+    }
+
+    #[test]
+    fn read_char_without_a_tty_applies_the_set_zero_eof_behavior() {
+        let mut a = VMCore::with_config(TapeConfig { eof_behavior: EofBehavior::SetZero, ..TapeConfig::default() });
+        a.data[0] = 42;
+        a.code.push(Opcodes::READ_CHAR);
+        a.compute_jumps(1).unwrap();
+        a.execute().unwrap();
+
+        assert_eq!(a.data[0], 0);
+    }
+
+    #[test]
+    fn read_char_without_a_tty_applies_the_set_minus_one_eof_behavior() {
+        let mut a = VMCore::with_config(TapeConfig { eof_behavior: EofBehavior::SetMinusOne, ..TapeConfig::default() });
+        a.code.push(Opcodes::READ_CHAR);
+        a.compute_jumps(1).unwrap();
+        a.execute().unwrap();
+
+        assert_eq!(a.data[0], 255);
+    }
+
+    #[test]
+    fn read_char_without_a_tty_leaves_the_cell_unchanged_by_default() {
+        let mut a = VMCore::new();
+        a.data[0] = 7;
+        a.code.push(Opcodes::READ_CHAR);
+        a.compute_jumps(1).unwrap();
+        a.execute().unwrap();
+
+        assert_eq!(a.data[0], 7);
+    }
+
+    #[test]
+    fn render_tape_highlights_the_current_cell() {
+        let mut a = VMCore::new();
+        a.data[0] = 1;
+        a.data[1] = 2;
+        a.data[2] = 3;
+        a.dp = 1;
 
-        assert_eq!(a.pc, 11);
+        assert_eq!(a.render_tape(0, 3), " 1 [2] 3 ");
     }
 }
@@ -7,12 +7,92 @@
 
 //----- crates
 use std::{process, env};
+use std::io::Write;
+use console::Term;
 
 
 //----- modules
 mod brainfuck;
 
 
+//----- functions
+
+// interactive step-debugger REPL: step, continue, break <pos>, print <start> <len>
+fn debug_repl(vm_core: &mut brainfuck::VMCore) {
+    let term = Term::stdout();
+
+    // `Term::read_line` never returns `Err` on EOF; when stdin isn't an
+    // attached tty (piped/redirected input) it returns `Ok("")` forever,
+    // which would otherwise spin the loop. Bail out up front instead.
+    if !term.is_term() {
+        return;
+    }
+
+    loop {
+        print!("(bf) ");
+        std::io::stdout().flush().ok();
+
+        let line = match term.read_line() {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+
+        let mut words = line.split_whitespace();
+        let result = match words.next() {
+            Some("step") | Some("s") => vm_core.step().map(|(pc, dp)| {
+                println!("pc={} dp={}", pc, dp);
+            }),
+
+            Some("continue") | Some("c") => vm_core.cont().map(|()| {
+                println!("pc={} dp={}", vm_core.pc(), vm_core.dp());
+            }),
+
+            Some("break") | Some("b") => {
+                match words.next().and_then(|pos| pos.parse::<usize>().ok()) {
+                    Some(pos) => {
+                        vm_core.set_breakpoint(pos);
+                        println!("breakpoint set at {}", pos);
+                    }
+                    None => println!("usage: break <pos>"),
+                }
+                Ok(())
+            }
+
+            Some("clear") => {
+                match words.next().and_then(|pos| pos.parse::<usize>().ok()) {
+                    Some(pos) => {
+                        vm_core.clear_breakpoint(pos);
+                        println!("breakpoint cleared at {}", pos);
+                    }
+                    None => println!("usage: clear <pos>"),
+                }
+                Ok(())
+            }
+
+            Some("print") | Some("p") => {
+                let start = words.next().and_then(|v| v.parse::<usize>().ok()).unwrap_or(0);
+                let len = words.next().and_then(|v| v.parse::<usize>().ok()).unwrap_or(16);
+                vm_core.dump_tape(start, len);
+                Ok(())
+            }
+
+            Some("quit") | Some("q") => break,
+
+            Some(other) => {
+                println!("unknown command: {}", other);
+                Ok(())
+            }
+
+            None => Ok(()),
+        };
+
+        if let Err(trap) = result {
+            println!("{}", vm_core.diagnostic(&trap));
+            break;
+        }
+    }
+}
+
 //----- main function
 fn main() {
 
@@ -25,20 +105,87 @@ fn main() {
         process::exit(1);
     }
 
-    // create a new Brainfuck Core VM
-    let mut vm_core: brainfuck::VMCore = brainfuck::VMCore::new();
+    // "--debug" drops into the step-debugger REPL instead of running straight
+    // through; "--auto-grow", "--no-wrap-left", "--cell-width <n>" and
+    // "--eof <zero|minus-one|unchanged>" configure the tape, and the
+    // remaining arguments are positional (filename, max cycles)
+    let mut debug = false;
+    let mut auto_grow = false;
+    let mut wrap_left = true;
+    let mut cell_width = brainfuck::CellWidth::Bits8;
+    let mut eof_behavior = brainfuck::EofBehavior::Unchanged;
+    let mut positional: Vec<String> = Vec::new();
+
+    let mut it = args.iter().skip(1);
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--debug" => debug = true,
+            "--auto-grow" => auto_grow = true,
+            "--no-wrap-left" => wrap_left = false,
+
+            "--cell-width" => {
+                cell_width = match it.next().and_then(|v| v.parse::<u32>().ok()) {
+                    Some(16) => brainfuck::CellWidth::Bits16,
+                    Some(32) => brainfuck::CellWidth::Bits32,
+                    _ => brainfuck::CellWidth::Bits8,
+                };
+            }
+
+            "--eof" => {
+                eof_behavior = match it.next().map(String::as_str) {
+                    Some("zero") => brainfuck::EofBehavior::SetZero,
+                    Some("minus-one") => brainfuck::EofBehavior::SetMinusOne,
+                    _ => brainfuck::EofBehavior::Unchanged,
+                };
+            }
+
+            other => positional.push(other.to_string()),
+        }
+    }
+
+    if positional.is_empty() {
+        println!("Please specify a source code file on the command line");
+        process::exit(1);
+    }
+
+    // create a new Brainfuck Core VM, configured per the command line (or
+    // with the default tape configuration, if none of those flags were given)
+    let default_config = brainfuck::TapeConfig::default();
+    let tape_config = brainfuck::TapeConfig { auto_grow, wrap_left, cell_width, eof_behavior, ..default_config };
+
+    let mut vm_core: brainfuck::VMCore = if tape_config == default_config {
+        brainfuck::VMCore::new()
+    } else {
+        brainfuck::VMCore::with_config(tape_config)
+    };
+
+    // an optional second argument caps the number of instructions the VM
+    // will dispatch, so a runaway loop gets reported instead of hanging
+    if let Some(max_cycles) = positional.get(1).and_then(|v| v.parse::<u64>().ok()) {
+        vm_core.set_max_cycles(Some(max_cycles));
+    }
 
     // read the code
-    let nbytes = match vm_core.load(&args[1]) {
+    let nbytes = match vm_core.load(&positional[0]) {
         Ok(n) => n,
-        Err(e) => {
-            println!("{}", e);
+        Err(trap) => {
+            println!("{}", vm_core.diagnostic(&trap));
             process::exit(1);
         }
     };
 
     println!("{} bytes read.", nbytes);
 
+    if debug {
+        debug_repl(&mut vm_core);
+        return;
+    }
+
     // execute the code
-    vm_core.execute();
-}
\ No newline at end of file
+    if let Err(trap) = vm_core.execute() {
+        println!("{}", vm_core.diagnostic(&trap));
+        process::exit(1);
+    }
+
+    println!("{} cycles executed.", vm_core.cycles());
+}